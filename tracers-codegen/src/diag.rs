@@ -0,0 +1,89 @@
+//! Structured, multi-span diagnostics for reporting problems found while validating provider
+//! traits.
+//!
+//! Unlike `TracersError`, which models a single failure as a string message with one span, a
+//! `Diagnostic` can carry a primary span plus any number of secondary "note"/"help" spans, and
+//! many `Diagnostic`s can be accumulated while scanning a trait so that every problem in it is
+//! reported in one pass instead of stopping at the first one found.
+use proc_macro2::TokenStream;
+use quote::quote_spanned;
+use syn::spanned::Spanned;
+
+/// A secondary annotation attached to a [`Diagnostic`], rendered as a `note:` or `help:` pointing
+/// at some span other than the diagnostic's primary one.
+#[derive(Debug, Clone)]
+pub(crate) struct DiagnosticNote {
+    span: proc_macro2::Span,
+    message: String,
+    is_help: bool,
+}
+
+/// One problem found while validating a provider trait, with enough span information to point
+/// `compile_error!` at the exact offending tokens rather than at the whole trait item.
+#[derive(Debug, Clone)]
+pub(crate) struct Diagnostic {
+    span: proc_macro2::Span,
+    message: String,
+    notes: Vec<DiagnosticNote>,
+}
+
+impl Diagnostic {
+    /// Creates a new diagnostic whose primary span is that of `spanned`.
+    pub(crate) fn new<T: Spanned>(spanned: &T, message: impl Into<String>) -> Self {
+        Diagnostic {
+            span: spanned.span(),
+            message: message.into(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Attaches a secondary `note:` pointing at `spanned`.
+    pub(crate) fn with_note<T: Spanned>(mut self, spanned: &T, message: impl Into<String>) -> Self {
+        self.notes.push(DiagnosticNote {
+            span: spanned.span(),
+            message: message.into(),
+            is_help: false,
+        });
+        self
+    }
+
+    /// Attaches a secondary `help:` pointing at `spanned`.
+    pub(crate) fn with_help<T: Spanned>(mut self, spanned: &T, message: impl Into<String>) -> Self {
+        self.notes.push(DiagnosticNote {
+            span: spanned.span(),
+            message: message.into(),
+            is_help: true,
+        });
+        self
+    }
+
+    /// The primary error message of this diagnostic, ignoring any attached notes/help.
+    pub(crate) fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Renders this diagnostic as one or more `compile_error!` invocations, each at the
+    /// appropriate span. Stable Rust has no multi-span diagnostic API, so a separate
+    /// `compile_error!` per span is the closest approximation: one at the primary span carrying
+    /// the main message, followed by one per note/help carrying a prefixed message at its own
+    /// span.
+    pub(crate) fn to_compile_errors(&self) -> TokenStream {
+        let message = &self.message;
+        let mut tokens = quote_spanned! { self.span => compile_error!(#message); };
+        for note in &self.notes {
+            let prefix = if note.is_help { "help" } else { "note" };
+            let message = format!("{}: {}", prefix, note.message);
+            tokens.extend(quote_spanned! { note.span => compile_error!(#message); });
+        }
+        tokens
+    }
+}
+
+/// Renders a whole collection of diagnostics as a single `TokenStream` of `compile_error!`
+/// invocations, suitable for returning directly from a proc macro.
+pub(crate) fn to_compile_errors(diagnostics: &[Diagnostic]) -> TokenStream {
+    diagnostics
+        .iter()
+        .map(Diagnostic::to_compile_errors)
+        .collect()
+}
@@ -0,0 +1,213 @@
+//! Generates DTrace/USDT build artifacts from a [`ProviderSpecification`]: a `.d` provider
+//! definition file consumable by `dtrace`/`bpftrace`, and a JSON manifest describing every
+//! provider, probe and argument for tooling that doesn't want to parse `.d` syntax.
+use crate::spec::probe::ProbeArgSpecification;
+use crate::spec::ProviderSpecification;
+use serde::Serialize;
+use syn::Type;
+
+/// Renders the `provider { ... };` definition for a single provider, using the same
+/// `name_with_hash()` that the generated Rust code uses for its USDT symbol names, so a `bpftrace`
+/// script built against this file matches the binary exactly.
+pub(crate) fn generate_provider_d(provider: &ProviderSpecification) -> String {
+    let mut out = format!("provider {} {{\n", provider.name_with_hash());
+
+    for probe in provider.probes() {
+        let args = probe
+            .args()
+            .iter()
+            .map(d_args_of)
+            .collect::<Vec<_>>()
+            .join(", ");
+        out.push_str(&format!("\tprobe {}({});\n", probe.name(), args));
+    }
+
+    out.push_str("};\n");
+    out
+}
+
+/// The D argument(s) a single probe argument expands to: a plain scalar/`&str` arg maps to one
+/// native D type, while a serialized structured arg is passed as a `(len, ptr)` pair and so maps
+/// to two.
+fn d_args_of(arg: &ProbeArgSpecification) -> String {
+    if arg.serialization().is_some() {
+        "uint64_t, char *".to_string()
+    } else {
+        d_type_of(arg.arg_type()).to_string()
+    }
+}
+
+/// Maps a probe argument's Rust type to the native D type `dtrace`/`bpftrace` expect to see it as.
+/// Falls back to `uint64_t` for any type this isn't taught to recognize, since an oversized
+/// integer is a safer default than a generation failure.
+fn d_type_of(ty: &Type) -> &'static str {
+    match type_name(ty).as_deref() {
+        Some("str") => "char *",
+        Some("String") => "char *",
+        Some("i8") => "int8_t",
+        Some("i16") => "int16_t",
+        Some("i32") => "int32_t",
+        Some("i64") | Some("isize") => "int64_t",
+        Some("u8") => "uint8_t",
+        Some("u16") => "uint16_t",
+        Some("u32") => "uint32_t",
+        Some("u64") | Some("usize") => "uint64_t",
+        Some("bool") => "int",
+        _ => "uint64_t",
+    }
+}
+
+/// Pulls the bare type name out of a (possibly referenced) probe argument type, e.g. `&str` and
+/// `str` both yield `Some("str")`.
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Reference(r) => type_name(&r.elem),
+        Type::Path(p) => p.path.segments.last().map(|seg| seg.ident.to_string()),
+        _ => None,
+    }
+}
+
+#[derive(Serialize)]
+struct ManifestArg {
+    name: String,
+    rust_type: String,
+    d_type: String,
+    serialization: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct ManifestProbe {
+    name: String,
+    doc: Vec<String>,
+    args: Vec<ManifestArg>,
+}
+
+#[derive(Serialize)]
+struct ManifestProvider {
+    name: String,
+    name_with_hash: String,
+    doc: Vec<String>,
+    probes: Vec<ManifestProbe>,
+}
+
+/// Builds the JSON-serializable manifest of every provider passed in, listing each provider's
+/// resolved name, probes, and argument names/types. Intended to be written out as a stable
+/// inventory file that build tooling can diff across releases.
+pub(crate) fn generate_manifest(providers: &[ProviderSpecification]) -> serde_json::Value {
+    let manifest_providers: Vec<ManifestProvider> = providers
+        .iter()
+        .map(|provider| ManifestProvider {
+            name: provider.name().to_string(),
+            name_with_hash: provider.name_with_hash(),
+            doc: provider.doc().to_vec(),
+            probes: provider
+                .probes()
+                .iter()
+                .map(|probe| ManifestProbe {
+                    name: probe.name().to_string(),
+                    doc: probe.doc().to_vec(),
+                    args: probe.args().iter().map(manifest_arg_of).collect(),
+                })
+                .collect(),
+        })
+        .collect();
+
+    serde_json::json!({ "providers": manifest_providers })
+}
+
+fn manifest_arg_of(arg: &ProbeArgSpecification) -> ManifestArg {
+    use crate::spec::probe::Format;
+    use quote::ToTokens;
+
+    ManifestArg {
+        name: arg.name().to_string(),
+        rust_type: arg.arg_type().clone().into_token_stream().to_string(),
+        // Mirror `d_args_of` rather than calling `d_type_of` directly, so a structured argument
+        // reports the actual two-parameter `(uint64_t, char *)` shape the `.d` file generates for
+        // it instead of a native type (frequently the `uint64_t` fallback) that doesn't match.
+        d_type: d_args_of(arg),
+        serialization: arg.serialization().map(|f| match f {
+            Format::Json => "json",
+            Format::Cbor => "cbor",
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use quote::quote;
+
+    fn provider_from(tokens: proc_macro2::TokenStream) -> ProviderSpecification {
+        ProviderSpecification::from_token_stream(tokens).unwrap()
+    }
+
+    #[test]
+    fn generates_probe_with_native_args() {
+        let provider = provider_from(quote! {
+            #[tracer]
+            trait TestProbes {
+                fn probe0();
+                fn probe1(foo: &str, count: u32);
+            }
+        });
+
+        let d = generate_provider_d(&provider);
+        assert!(d.starts_with(&format!("provider {} {{\n", provider.name_with_hash())));
+        assert!(d.contains("\tprobe probe0();\n"));
+        assert!(d.contains("\tprobe probe1(char *, uint32_t);\n"));
+        assert!(d.ends_with("};\n"));
+    }
+
+    #[test]
+    fn generates_probe_with_serialized_arg_as_len_ptr_pair() {
+        let provider = provider_from(quote! {
+            #[tracer]
+            trait TestProbes {
+                fn probe1(#[arg_fmt(json)] payload: MyStruct);
+            }
+        });
+
+        let d = generate_provider_d(&provider);
+        assert!(d.contains("\tprobe probe1(uint64_t, char *);\n"));
+    }
+
+    #[test]
+    fn manifest_lists_providers_probes_and_args() {
+        let provider = provider_from(quote! {
+            #[tracer]
+            /// A test provider.
+            trait TestProbes {
+                /// Fires with a count.
+                fn probe1(count: u32);
+            }
+        });
+
+        let manifest = generate_manifest(&[provider]);
+        let providers = manifest["providers"].as_array().unwrap();
+        assert_eq!(1, providers.len());
+        assert_eq!("A test provider.", providers[0]["doc"][0]);
+
+        let probes = providers[0]["probes"].as_array().unwrap();
+        assert_eq!("probe1", probes[0]["name"]);
+        assert_eq!("Fires with a count.", probes[0]["doc"][0]);
+        assert_eq!("count", probes[0]["args"][0]["name"]);
+        assert_eq!("uint32_t", probes[0]["args"][0]["d_type"]);
+        assert!(probes[0]["args"][0]["serialization"].is_null());
+    }
+
+    #[test]
+    fn manifest_agrees_with_d_file_on_serialized_arg_shape() {
+        let provider = provider_from(quote! {
+            #[tracer]
+            trait TestProbes {
+                fn probe1(#[arg_fmt(cbor)] payload: MyStruct);
+            }
+        });
+
+        let manifest = generate_manifest(&[provider]);
+        let arg = &manifest["providers"][0]["probes"][0]["args"][0];
+        assert_eq!("uint64_t, char *", arg["d_type"]);
+        assert_eq!("cbor", arg["serialization"]);
+    }
+}
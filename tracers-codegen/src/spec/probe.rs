@@ -0,0 +1,454 @@
+//! Defines `ProbeSpecification`, which models a single probe method declared on a provider trait:
+//! its name, its arguments, and the documentation and stability metadata the provider author
+//! attached to it. `find_probes` in the `provider` module builds one of these for every method on
+//! a `#[tracer]` trait that turns out to be a valid probe.
+use crate::diag::Diagnostic;
+use crate::serde_helpers;
+use quote::ToTokens;
+use serde::{Deserialize, Serialize};
+use syn::{FnArg, ItemTrait, Lit, Meta, NestedMeta, Pat, TraitItemMethod};
+
+/// Whether a probe is a stable, committed tracepoint or one that may still change or disappear.
+///
+/// Parsed from an optional `#[stable(since = "...")]` / `#[unstable(reason = "...")]` attribute on
+/// the probe method. A probe with neither attribute is `Unstable` with no reason given, since
+/// silently promising stability by default would be the more dangerous failure mode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum StabilityLevel {
+    Stable { since: String },
+    Unstable { reason: Option<String> },
+}
+
+impl Default for StabilityLevel {
+    fn default() -> Self {
+        StabilityLevel::Unstable { reason: None }
+    }
+}
+
+/// The wire format a structured probe argument is serialized into before being passed to the
+/// probe, for consumers that want to decode it back into the original value.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Format {
+    Json,
+    Cbor,
+}
+
+/// One argument to a probe method, with enough type information to generate the native probe
+/// firing code.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ProbeArgSpecification {
+    name: String,
+    #[serde(with = "serde_helpers::syn")]
+    arg_type: syn::Type,
+
+    /// `Some(format)` if this argument is a structured type serialized with `format` and passed
+    /// to the probe as a `(len, ptr)` byte-buffer pair, parsed from a `#[arg_fmt(...)]` attribute
+    /// on the parameter. `None` for a plain scalar/`&str` argument that takes the fast native
+    /// path.
+    serialization: Option<Format>,
+}
+
+impl ProbeArgSpecification {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn arg_type(&self) -> &syn::Type {
+        &self.arg_type
+    }
+
+    pub(crate) fn serialization(&self) -> Option<Format> {
+        self.serialization
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ProbeSpecification {
+    name: String,
+    args: Vec<ProbeArgSpecification>,
+
+    /// The prose of each `#[doc = "..."]` line attached to the probe method, in source order.
+    /// Empty if the probe has no doc comment.
+    doc: Vec<String>,
+
+    /// Whether this probe is a committed part of the provider's public API, or still subject to
+    /// change. Defaults to `Unstable` when the method carries neither a `#[stable]` nor an
+    /// `#[unstable]` attribute.
+    stability: StabilityLevel,
+}
+
+impl ProbeSpecification {
+    /// Builds a probe spec from a single method on a provider trait, accumulating a
+    /// [`Diagnostic`] for every problem found (an unparseable `#[stable]`/`#[unstable]` attribute,
+    /// a malformed `#[arg_fmt(...)]`, ...) instead of bailing out on the first one, consistent with
+    /// how `find_probes` treats the rest of the trait. The first element of the tuple is `None`
+    /// only when the method itself can't be a probe at all (e.g. it takes `self`); a merely
+    /// malformed attribute still yields a spec, just with that field left at its default and a
+    /// diagnostic recorded explaining why.
+    pub(crate) fn from_method(
+        _item: &ItemTrait,
+        method: &TraitItemMethod,
+    ) -> (Option<ProbeSpecification>, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let name = method.sig.ident.to_string();
+
+        let mut args = Vec::new();
+        let mut takes_self = false;
+        for input in method.sig.inputs.iter() {
+            match input {
+                FnArg::Typed(pat_type) => {
+                    let arg_name = match &*pat_type.pat {
+                        Pat::Ident(pat_ident) => pat_ident.ident.to_string(),
+                        other => other.clone().into_token_stream().to_string(),
+                    };
+                    let serialization = arg_format(&pat_type.attrs, &mut diagnostics);
+                    args.push(ProbeArgSpecification {
+                        name: arg_name,
+                        arg_type: (*pat_type.ty).clone(),
+                        serialization,
+                    });
+                }
+                FnArg::Receiver(_) => {
+                    diagnostics.push(Diagnostic::new(
+                        method,
+                        "Probe methods must not take a `self` parameter",
+                    ));
+                    takes_self = true;
+                }
+            }
+        }
+
+        let stability = stability_level(&method.attrs, &mut diagnostics);
+
+        if takes_self {
+            return (None, diagnostics);
+        }
+
+        (
+            Some(ProbeSpecification {
+                name,
+                args,
+                doc: doc_comment(&method.attrs),
+                stability,
+            }),
+            diagnostics,
+        )
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn args(&self) -> &[ProbeArgSpecification] {
+        &self.args
+    }
+
+    pub(crate) fn doc(&self) -> &[String] {
+        &self.doc
+    }
+
+    pub(crate) fn stability(&self) -> &StabilityLevel {
+        &self.stability
+    }
+
+    /// The name this probe's "is enabled" predicate would have, e.g. `probe1_enabled`, reserved
+    /// here so that the codegen and `probe!` macro sides of lazy, enablement-gated argument
+    /// evaluation (USDT-semaphore-backed, guarding argument evaluation behind `if
+    /// probeN_enabled() { ... }`) have an agreed-upon name to generate against. Nothing in this
+    /// crate emits that function or semaphore yet, nor does anything guard argument evaluation on
+    /// it — this is scaffolding for that follow-up work, not the feature itself.
+    ///
+    /// This alone does not implement lazy, enablement-gated argument evaluation: the actual
+    /// semaphore-backed `probeN_enabled()` codegen and the `probe!`-macro-side closure wrapping
+    /// live in the `probe!` macro and code-generator crates, neither of which exist in this source
+    /// tree. That work is still open and should not be considered done on the strength of this
+    /// name reservation.
+    pub(crate) fn enabled_fn_name(&self) -> String {
+        format!("{}_enabled", self.name)
+    }
+}
+
+/// Extracts the text of every `#[doc = "..."]` attribute on `attrs`, which is how both `///` and
+/// `/** */` doc comments are represented once parsed by `syn`. Shared with `ProviderSpecification`,
+/// which uses it to capture the doc comment on the provider trait itself.
+pub(crate) fn doc_comment(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter_map(|attr| {
+            if !attr.path.is_ident("doc") {
+                return None;
+            }
+
+            match attr.parse_meta() {
+                Ok(Meta::NameValue(nv)) => match nv.lit {
+                    Lit::Str(s) => Some(s.value().trim().to_string()),
+                    _ => None,
+                },
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Looks for a `#[stable(since = "...")]` or `#[unstable(reason = "...")]` attribute on a probe
+/// method and parses it into a `StabilityLevel`, defaulting to `Unstable { reason: None }` when
+/// neither is present or the attribute found is malformed; malformed attributes push a
+/// [`Diagnostic`] pointing at the attribute rather than aborting the whole method.
+fn stability_level(attrs: &[syn::Attribute], diagnostics: &mut Vec<Diagnostic>) -> StabilityLevel {
+    for attr in attrs {
+        if attr.path.is_ident("stable") {
+            let meta = match attr.parse_meta() {
+                Ok(meta) => meta,
+                Err(e) => {
+                    diagnostics.push(Diagnostic::new(
+                        attr,
+                        format!("Invalid `#[stable(...)]` attribute: {}", e),
+                    ));
+                    return StabilityLevel::default();
+                }
+            };
+            return match name_value_str(&meta, "since") {
+                Some(since) => StabilityLevel::Stable { since },
+                None => {
+                    diagnostics.push(Diagnostic::new(
+                        attr,
+                        "`#[stable]` requires a `since = \"...\"` argument",
+                    ));
+                    StabilityLevel::default()
+                }
+            };
+        } else if attr.path.is_ident("unstable") {
+            let meta = match attr.parse_meta() {
+                Ok(meta) => meta,
+                Err(e) => {
+                    diagnostics.push(Diagnostic::new(
+                        attr,
+                        format!("Invalid `#[unstable(...)]` attribute: {}", e),
+                    ));
+                    return StabilityLevel::default();
+                }
+            };
+            return StabilityLevel::Unstable {
+                reason: name_value_str(&meta, "reason"),
+            };
+        }
+    }
+
+    StabilityLevel::default()
+}
+
+/// Looks for a `#[arg_fmt(json)]` / `#[arg_fmt(cbor)]` attribute on a probe parameter, indicating
+/// that it's a structured type to be serialized rather than passed natively. Returns `None` if
+/// the parameter carries no such attribute, or if the one it carries is malformed — in the latter
+/// case a [`Diagnostic`] pointing at the attribute is pushed rather than aborting the whole probe
+/// method over one bad parameter attribute.
+fn arg_format(attrs: &[syn::Attribute], diagnostics: &mut Vec<Diagnostic>) -> Option<Format> {
+    for attr in attrs {
+        if !attr.path.is_ident("arg_fmt") {
+            continue;
+        }
+
+        let meta = match attr.parse_meta() {
+            Ok(meta) => meta,
+            Err(e) => {
+                diagnostics.push(Diagnostic::new(
+                    attr,
+                    format!("Invalid `#[arg_fmt(...)]` attribute: {}", e),
+                ));
+                return None;
+            }
+        };
+
+        if let Meta::List(list) = &meta {
+            if let Some(NestedMeta::Meta(Meta::Path(path))) = list.nested.first() {
+                if path.is_ident("json") {
+                    return Some(Format::Json);
+                } else if path.is_ident("cbor") {
+                    return Some(Format::Cbor);
+                }
+            }
+        }
+
+        diagnostics.push(Diagnostic::new(
+            attr,
+            "`#[arg_fmt(...)]` expects a single format, either `json` or `cbor`",
+        ));
+        return None;
+    }
+
+    None
+}
+
+/// Given a parsed `#[attr(key = "value", ...)]` meta list, finds `key` and returns its string
+/// value, if present.
+fn name_value_str(meta: &Meta, key: &str) -> Option<String> {
+    if let Meta::List(list) = meta {
+        for nested in list.nested.iter() {
+            if let NestedMeta::Meta(Meta::NameValue(nv)) = nested {
+                if nv.path.is_ident(key) {
+                    if let Lit::Str(s) = &nv.lit {
+                        return Some(s.value());
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syn::parse_quote;
+
+    fn from_method(method: syn::TraitItemMethod) -> (Option<ProbeSpecification>, Vec<Diagnostic>) {
+        let item: ItemTrait = parse_quote! {
+            trait TestProbes {}
+        };
+        ProbeSpecification::from_method(&item, &method)
+    }
+
+    #[test]
+    fn captures_doc_comment() {
+        let method: syn::TraitItemMethod = parse_quote! {
+            /// Fires when a widget is frobnicated.
+            /// Takes the widget's id.
+            fn probe1(id: u64);
+        };
+
+        let (spec, diagnostics) = from_method(method);
+        assert!(diagnostics.is_empty());
+        assert_eq!(
+            vec![
+                "Fires when a widget is frobnicated.".to_string(),
+                "Takes the widget's id.".to_string(),
+            ],
+            spec.unwrap().doc
+        );
+    }
+
+    #[test]
+    fn defaults_to_unstable_with_no_reason() {
+        let method: syn::TraitItemMethod = parse_quote! {
+            fn probe1();
+        };
+
+        let (spec, diagnostics) = from_method(method);
+        assert!(diagnostics.is_empty());
+        assert_eq!(
+            StabilityLevel::Unstable { reason: None },
+            spec.unwrap().stability
+        );
+    }
+
+    #[test]
+    fn parses_stable_attribute() {
+        let method: syn::TraitItemMethod = parse_quote! {
+            #[stable(since = "1.2.0")]
+            fn probe1();
+        };
+
+        let (spec, diagnostics) = from_method(method);
+        assert!(diagnostics.is_empty());
+        assert_eq!(
+            StabilityLevel::Stable {
+                since: "1.2.0".to_string()
+            },
+            spec.unwrap().stability
+        );
+    }
+
+    #[test]
+    fn parses_unstable_attribute_with_reason() {
+        let method: syn::TraitItemMethod = parse_quote! {
+            #[unstable(reason = "still tuning the argument list")]
+            fn probe1();
+        };
+
+        let (spec, diagnostics) = from_method(method);
+        assert!(diagnostics.is_empty());
+        assert_eq!(
+            StabilityLevel::Unstable {
+                reason: Some("still tuning the argument list".to_string())
+            },
+            spec.unwrap().stability
+        );
+    }
+
+    #[test]
+    fn stable_without_since_is_a_diagnostic_not_a_bail() {
+        let method: syn::TraitItemMethod = parse_quote! {
+            #[stable]
+            fn probe1();
+        };
+
+        let (spec, diagnostics) = from_method(method);
+        assert!(
+            spec.is_some(),
+            "a malformed #[stable] should not drop the whole probe"
+        );
+        assert_eq!(StabilityLevel::default(), spec.unwrap().stability);
+        assert_eq!(1, diagnostics.len());
+        assert!(diagnostics[0].message().contains("since"));
+    }
+
+    #[test]
+    fn plain_args_have_no_serialization_format() {
+        let method: syn::TraitItemMethod = parse_quote! {
+            fn probe1(id: u64);
+        };
+
+        let (spec, diagnostics) = from_method(method);
+        assert!(diagnostics.is_empty());
+        assert_eq!(None, spec.unwrap().args[0].serialization());
+    }
+
+    #[test]
+    fn parses_json_arg_fmt() {
+        let method: syn::TraitItemMethod = parse_quote! {
+            fn probe1(#[arg_fmt(json)] payload: MyStruct);
+        };
+
+        let (spec, diagnostics) = from_method(method);
+        assert!(diagnostics.is_empty());
+        assert_eq!(Some(Format::Json), spec.unwrap().args[0].serialization());
+    }
+
+    #[test]
+    fn parses_cbor_arg_fmt() {
+        let method: syn::TraitItemMethod = parse_quote! {
+            fn probe1(#[arg_fmt(cbor)] payload: MyStruct);
+        };
+
+        let (spec, diagnostics) = from_method(method);
+        assert!(diagnostics.is_empty());
+        assert_eq!(Some(Format::Cbor), spec.unwrap().args[0].serialization());
+    }
+
+    #[test]
+    fn unrecognized_arg_fmt_is_a_diagnostic_not_a_bail() {
+        let method: syn::TraitItemMethod = parse_quote! {
+            fn probe1(#[arg_fmt(xml)] payload: MyStruct);
+        };
+
+        let (spec, diagnostics) = from_method(method);
+        assert!(
+            spec.is_some(),
+            "a malformed #[arg_fmt] should not drop the whole probe"
+        );
+        assert_eq!(None, spec.unwrap().args[0].serialization());
+        assert_eq!(1, diagnostics.len());
+        assert!(diagnostics[0].message().contains("json"));
+    }
+
+    #[test]
+    fn enabled_fn_name_is_derived_from_probe_name() {
+        let method: syn::TraitItemMethod = parse_quote! {
+            fn probe1(foo: &str);
+        };
+
+        let (spec, _) = from_method(method);
+        assert_eq!("probe1_enabled", spec.unwrap().enabled_fn_name());
+    }
+}
@@ -2,8 +2,10 @@
 //! `tracers` provider traits therein, as well as analyze those traits and produce `ProbeSpec`s for
 //! each of the probes they contain.  Once the provider traits have been discovered, other modules
 //! in this crate can then process them in various ways
+use crate::diag::Diagnostic;
 use crate::hashing::HashCode;
 use crate::serde_helpers;
+use crate::spec::probe::doc_comment;
 use crate::spec::ProbeSpecification;
 use crate::{TracersError, TracersResult};
 use heck::SnakeCase;
@@ -13,7 +15,7 @@ use quote::ToTokens;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use syn::visit::Visit;
-use syn::{ItemTrait, TraitItem};
+use syn::{ItemTrait, Lit, Meta, NestedMeta, TraitItem};
 
 #[derive(Serialize, Deserialize)]
 pub struct ProviderSpecification {
@@ -24,6 +26,23 @@ pub struct ProviderSpecification {
     #[serde(with = "serde_helpers::token_stream")]
     token_stream: TokenStream,
     probes: Vec<ProbeSpecification>,
+
+    /// Whether this provider should start out disabled by default, requiring an explicit opt-in
+    /// at runtime before any of its probes can fire. Set via
+    /// `#[tracer(disabled_default = true)]`; `false` unless configured otherwise.
+    disabled_default: bool,
+
+    /// The prose of each `#[doc = "..."]` line attached to the provider trait itself, in source
+    /// order. Empty if the trait has no doc comment.
+    doc: Vec<String>,
+
+    /// Every problem found while validating this provider's trait, each carrying its own span(s)
+    /// so the `#[tracer]` proc macro can report them all in one pass instead of bailing out after
+    /// the first. Empty for a trait that passed validation cleanly. `Diagnostic` carries
+    /// `proc_macro2::Span`s which have no serde impl, so this is never persisted; a spec loaded
+    /// back from a manifest is necessarily one that already passed validation.
+    #[serde(skip)]
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl fmt::Debug for ProviderSpecification {
@@ -53,19 +72,49 @@ impl ProviderSpecification {
     }
 
     pub fn from_trait(item_trait: &ItemTrait) -> TracersResult<ProviderSpecification> {
-        let probes = find_probes(item_trait)?;
+        let mut diagnostics = Vec::new();
+        let config = ProviderConfig::from_attrs(&item_trait.attrs, &mut diagnostics);
+        let (probes, mut probe_diagnostics) = find_probes(item_trait);
+        diagnostics.append(&mut probe_diagnostics);
+
+        let name = config
+            .name
+            .unwrap_or_else(|| Self::provider_name_from_trait(&item_trait.ident));
         let token_stream = quote! { #item_trait };
         let hash = crate::hashing::hash_token_stream(&token_stream);
         Ok(ProviderSpecification {
-            name: Self::provider_name_from_trait(&item_trait.ident),
+            name,
             hash,
             item_trait: item_trait.clone(),
             token_stream,
             probes,
+            disabled_default: config.disabled_default,
+            doc: doc_comment(&item_trait.attrs),
+            diagnostics,
         })
     }
 
-    /// Computes the name of a provider given the name of the provider's trait.
+    /// Whether this provider starts out disabled by default; see the `disabled_default` field.
+    pub(crate) fn disabled_by_default(&self) -> bool {
+        self.disabled_default
+    }
+
+    /// The prose of the provider trait's own doc comment, one entry per source line. Empty if the
+    /// trait has none.
+    pub(crate) fn doc(&self) -> &[String] {
+        &self.doc
+    }
+
+    /// Every problem found while validating this provider's trait. Empty means the trait is a
+    /// valid provider and `probes()` can be trusted; non-empty means `probes()` may be incomplete
+    /// and the `#[tracer]`/`#[prober]` proc macro should render these as `compile_error!`s instead
+    /// of proceeding with code generation.
+    pub(crate) fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Computes the default name of a provider given the name of the provider's trait, used
+    /// unless the trait overrides it with `#[tracer(provider = "...")]`.
     ///
     pub(crate) fn provider_name_from_trait(ident: &syn::Ident) -> String {
         // The provider name must be chosen carefully.  As of this writing (2019-04) the `bpftrace`
@@ -74,7 +123,10 @@ impl ProviderSpecification {
         // then, the provider name is just the name of the provider trait, converted into
         // snake_case for consistency with USDT naming conventions.  If two modules in the same
         // process have the same provider name, they will conflict and some unspecified `bad
-        // things` will happen.
+        // things` will happen; `#[tracer(provider = "...")]` overrides are validated against the
+        // same dots/colons restriction, and `validate_provider_names` below checks for collisions
+        // among the providers found in a single file; providers in different files are not
+        // compared with each other.
         ident.to_string().to_snake_case()
     }
 
@@ -122,19 +174,156 @@ impl ProviderSpecification {
                 item_trait: self.item_trait,
                 token_stream: self.token_stream,
                 probes: Vec::new(),
+                disabled_default: self.disabled_default,
+                doc: self.doc,
+                diagnostics: self.diagnostics,
             },
             probes,
         )
     }
 }
 
+/// The provider-wide configuration parsed out of a trait's `#[tracer(...)]` attribute, e.g.
+/// `#[tracer(provider = "my_provider", disabled_default = true)]`. A bare `#[tracer]` with no
+/// arguments, or one that isn't present at all, yields the all-default config.
+#[derive(Default)]
+struct ProviderConfig {
+    /// Overrides the USDT provider name that would otherwise be derived from the trait's name.
+    name: Option<String>,
+    disabled_default: bool,
+}
+
+impl ProviderConfig {
+    /// Parses the `#[tracer(...)]` attribute, if any, out of `attrs`. Problems with the
+    /// attribute's contents (an invalid provider name, an unparseable meta list) are pushed onto
+    /// `diagnostics` rather than failing outright, consistent with how `find_probes` accumulates
+    /// problems instead of bailing out at the first one.
+    fn from_attrs(attrs: &[syn::Attribute], diagnostics: &mut Vec<Diagnostic>) -> ProviderConfig {
+        let mut config = ProviderConfig::default();
+
+        // Match `tracers::tracer` as well as a bare `tracer`, the same way `find_providers` does.
+        let attr = match attrs.iter().find(|a| match a.path.segments.iter().last() {
+            Some(syn::PathSegment { ident, .. }) => *ident == "tracer",
+            None => false,
+        }) {
+            Some(attr) => attr,
+            None => return config,
+        };
+
+        let meta = match attr.parse_meta() {
+            Ok(meta) => meta,
+            Err(e) => {
+                diagnostics.push(Diagnostic::new(
+                    attr,
+                    format!("Invalid `#[tracer(...)]` attribute: {}", e),
+                ));
+                return config;
+            }
+        };
+
+        let list = match meta {
+            // `#[tracer]` with no parens; nothing to configure.
+            Meta::Path(_) => return config,
+            Meta::List(list) => list,
+            Meta::NameValue(_) => {
+                diagnostics.push(Diagnostic::new(
+                    attr,
+                    "`#[tracer]` does not take a value directly; use `#[tracer(provider = \"...\")]`",
+                ));
+                return config;
+            }
+        };
+
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("provider") => {
+                    match &nv.lit {
+                        Lit::Str(s) => {
+                            let name = s.value();
+                            if name.contains('.') || name.contains(':') {
+                                diagnostics.push(Diagnostic::new(
+                                    nv,
+                                    "Provider names can't contain '.' or ':'; bpftrace and bcc don't support them in USDT provider names",
+                                ));
+                            } else {
+                                config.name = Some(name);
+                            }
+                        }
+                        _ => diagnostics
+                            .push(Diagnostic::new(nv, "`provider` must be a string literal")),
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("disabled_default") => {
+                    match &nv.lit {
+                        Lit::Bool(b) => config.disabled_default = b.value,
+                        _ => diagnostics.push(Diagnostic::new(
+                            nv,
+                            "`disabled_default` must be a bool literal",
+                        )),
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) => {
+                    diagnostics.push(Diagnostic::new(
+                        nv,
+                        format!(
+                            "Unrecognized `#[tracer(...)]` argument '{}'",
+                            nv.path.to_token_stream()
+                        ),
+                    ));
+                }
+                // A bare word (`#[tracer(disabled_default)]`), a literal, or a nested list are
+                // all not `key = value` and so aren't anything `#[tracer(...)]` understands.
+                other => {
+                    diagnostics.push(Diagnostic::new(
+                        other,
+                        "`#[tracer(...)]` arguments must be of the form `key = value`",
+                    ));
+                }
+            }
+        }
+
+        config
+    }
+}
+
+/// Checks a set of providers destined for the same build for USDT provider name collisions, since
+/// two providers that resolve to the same name will silently conflict at runtime instead of
+/// failing to compile. Called by `find_providers` on every set of providers it discovers in a
+/// file; pushes a diagnostic directly onto each later provider whose resolved name collides with
+/// one already seen, so the `#[tracer]` proc macro reports the conflict when expanding that
+/// trait.
+fn validate_provider_names(providers: &mut [ProviderSpecification]) {
+    let mut seen: std::collections::HashMap<String, syn::Ident> = std::collections::HashMap::new();
+
+    for provider in providers.iter_mut() {
+        match seen.get(&provider.name) {
+            Some(first_ident) => {
+                let diagnostic = Diagnostic::new(
+                    &provider.item_trait.ident,
+                    format!(
+                        "Provider name '{}' collides with provider trait `{}`; USDT provider names must be unique within a build",
+                        provider.name, first_ident
+                    ),
+                );
+                provider.diagnostics.push(diagnostic);
+            }
+            None => {
+                seen.insert(provider.name.clone(), provider.item_trait.ident.clone());
+            }
+        }
+    }
+}
+
 /// Scans the AST of a Rust source file, finding all traits marked with the `tracer` attribute,
 /// parses the contents of the trait, and deduces the provider spec from that.
 ///
-/// Note that if any traits are encountered with the `tracer` attribute but which are in some way
-/// invalid as providers, those traits will be silently ignored.  At compile time the `tracer`
-/// attribute will cause a very detailed compile error so there's no chance the user will miss this
-/// mistake.
+/// Every trait found with the `tracer` attribute is returned, even ones that turn out to be
+/// invalid as providers; check `ProviderSpecification::diagnostics()` on each to see whether it
+/// is. Accumulating all of them here, rather than silently dropping the invalid ones, lets the
+/// `tracer` attribute surface every problem it found as a `compile_error!` in one pass instead of
+/// reporting only the first. Before returning, also runs `validate_provider_names` over the whole
+/// set so that two providers in the same file which resolve to the same USDT name get a
+/// diagnostic instead of silently conflicting at runtime.
 pub(crate) fn find_providers(ast: &syn::File) -> Vec<ProviderSpecification> {
     //Construct an implementation of the `syn` crate's `Visit` trait which will examine all trait
     //declarations in the file looking for possible providers
@@ -155,7 +344,9 @@ pub(crate) fn find_providers(ast: &syn::File) -> Vec<ProviderSpecification> {
                     _ => false,
                 })
             {
-                //This looks like a provider trait
+                //This looks like a provider trait.  `from_trait` does not return an error for a
+                //parsed `ItemTrait`; any problems with its *contents* are reported as diagnostics
+                //on the resulting spec instead.
                 if let Ok(provider) = ProviderSpecification::from_trait(i) {
                     self.providers.push(provider)
                 }
@@ -168,39 +359,67 @@ pub(crate) fn find_providers(ast: &syn::File) -> Vec<ProviderSpecification> {
     };
     visitor.visit_file(ast);
 
-    visitor.providers
+    let mut providers = visitor.providers;
+    validate_provider_names(&mut providers);
+    providers
 }
 
 /// Looking at the methods defined on the trait, deduce from those methods the probes that we will
 /// need to define, including their arg counts and arg types.
 ///
-/// If the trait contains anything other than method declarations, or any of the declarations are
-/// not suitable as probes, an error is returned
-fn find_probes(item: &ItemTrait) -> TracersResult<Vec<ProbeSpecification>> {
-    if item.generics.type_params().next() != None || item.generics.lifetimes().next() != None {
-        return Err(TracersError::invalid_provider(
-            "Probe traits must not take any lifetime or type parameters",
-            item,
-        ));
+/// Unlike a fail-fast validation pass, this keeps going after finding a problem so that every
+/// issue in the trait is captured as its own [`Diagnostic`] with a precise span: the generic
+/// parameter list when the trait takes lifetime/type params it shouldn't, or the individual item
+/// when it's something other than a method. The returned `Vec<ProbeSpecification>` contains every
+/// method that `ProbeSpecification::from_method` could turn into a probe, including ones whose
+/// attributes were malformed (those keep default values for the affected fields); methods that
+/// take `self` are left out. If the diagnostics vec is non-empty the probe list should not be
+/// treated as complete.
+fn find_probes(item: &ItemTrait) -> (Vec<ProbeSpecification>, Vec<Diagnostic>) {
+    let mut specs: Vec<ProbeSpecification> = Vec::new();
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    if let Some(lifetime) = item.generics.lifetimes().next() {
+        diagnostics.push(
+            Diagnostic::new(
+                &item.generics,
+                "Probe traits must not take any lifetime or type parameters",
+            )
+            .with_note(lifetime, "lifetime parameter not allowed here"),
+        );
+    }
+    if let Some(type_param) = item.generics.type_params().next() {
+        diagnostics.push(
+            Diagnostic::new(
+                &item.generics,
+                "Probe traits must not take any lifetime or type parameters",
+            )
+            .with_note(type_param, "type parameter not allowed here"),
+        );
     }
 
-    // Look at the methods on the trait and translate each one into a probe specification
-    let mut specs: Vec<ProbeSpecification> = Vec::new();
+    // Look at the methods on the trait and translate each one into a probe specification,
+    // recording a diagnostic (rather than bailing out) for anything that isn't a valid probe
+    // method so the rest of the trait still gets scanned.
     for f in item.items.iter() {
         match f {
             TraitItem::Method(ref m) => {
-                specs.push(ProbeSpecification::from_method(item, m)?);
+                let (spec, mut method_diagnostics) = ProbeSpecification::from_method(item, m);
+                diagnostics.append(&mut method_diagnostics);
+                if let Some(spec) = spec {
+                    specs.push(spec);
+                }
             }
             _ => {
-                return Err(TracersError::invalid_provider(
-                    "Probe traits must consist entirely of methods, no other contents",
+                diagnostics.push(Diagnostic::new(
                     f,
+                    "Probe traits must consist entirely of methods, no other contents",
                 ));
             }
         }
     }
 
-    Ok(specs)
+    (specs, diagnostics)
 }
 
 #[cfg(test)]
@@ -233,7 +452,7 @@ mod test {
     }
 
     #[test]
-    fn find_providers_ignores_invalid_traits() {
+    fn find_providers_flags_invalid_traits_with_diagnostics() {
         for test_trait in get_filtered_test_traits(true) {
             let trait_decl = test_trait.tokenstream;
             let test_file: syn::File = parse_quote! {
@@ -241,10 +460,16 @@ mod test {
                 #trait_decl
             };
 
+            let providers = find_providers(&test_file);
             assert_eq!(
-                None,
-                find_providers(&test_file).first(),
-                "The invalid trait '{}' was returned by find_providers as valid",
+                1,
+                providers.len(),
+                "The invalid trait '{}' was not returned by find_providers at all",
+                test_trait.description
+            );
+            assert!(
+                !providers[0].diagnostics().is_empty(),
+                "The invalid trait '{}' was returned by find_providers with no diagnostics",
                 test_trait.description
             );
         }
@@ -272,7 +497,7 @@ mod test {
     }
 
     #[test]
-    fn find_probes_fails_with_invalid_traits() {
+    fn find_probes_reports_diagnostics_for_invalid_traits() {
         for test_trait in get_filtered_test_traits(true) {
             let trait_decl = test_trait.tokenstream;
             let item_trait: syn::ItemTrait = parse_quote! {
@@ -280,24 +505,41 @@ mod test {
                 #trait_decl
             };
 
-            let error = find_probes(&item_trait).err();
-            assert_ne!(
-                None, error,
-                "The invalid trait '{}' was returned by find_probes as valid",
+            let (_, diagnostics) = find_probes(&item_trait);
+            assert!(
+                !diagnostics.is_empty(),
+                "The invalid trait '{}' was processed by find_probes with no diagnostics",
                 test_trait.description
             );
 
             let expected_error_substring = test_trait.expected_error.unwrap();
-            let message = error.unwrap().to_string();
-            assert!(message.contains(expected_error_substring),
-                "The invalid trait '{}' should produce an error containing '{}' but instead it produced '{}'",
+            assert!(
+                diagnostics
+                    .iter()
+                    .any(|d| d.message().contains(expected_error_substring)),
+                "The invalid trait '{}' should produce a diagnostic containing '{}' but none did",
                 test_trait.description,
                 expected_error_substring,
-                message
             );
         }
     }
 
+    #[test]
+    fn find_probes_reports_both_lifetime_and_type_param_diagnostics() {
+        let item_trait: syn::ItemTrait = parse_quote! {
+            trait Foo<'a, T> {
+                fn probe0();
+            }
+        };
+
+        let (_, diagnostics) = find_probes(&item_trait);
+        assert_eq!(
+            2,
+            diagnostics.len(),
+            "a trait with both a lifetime and a type parameter should get a diagnostic for each, not just the first"
+        );
+    }
+
     #[test]
     fn find_probes_succeeds_with_valid_traits() {
         for test_trait in get_filtered_test_traits(false) {
@@ -307,11 +549,58 @@ mod test {
                 #trait_decl
             };
 
-            let probes = find_probes(&item_trait).unwrap();
+            let (probes, diagnostics) = find_probes(&item_trait);
+            assert!(
+                diagnostics.is_empty(),
+                "The valid trait '{}' unexpectedly produced diagnostics",
+                test_trait.description
+            );
             assert_eq!(probes, test_trait.probes.unwrap_or(Vec::new()));
         }
     }
 
+    #[test]
+    fn find_providers_flags_colliding_provider_names() {
+        let test_file: syn::File = parse_quote! {
+            #[tracer]
+            trait FooProvider {
+                fn probe0();
+            }
+
+            #[tracer(provider = "foo_provider")]
+            trait BarProvider {
+                fn probe0();
+            }
+        };
+
+        let providers = find_providers(&test_file);
+        assert_eq!(2, providers.len());
+        assert!(
+            providers[0].diagnostics().is_empty(),
+            "the first provider to claim a name should not be flagged"
+        );
+        assert_eq!(1, providers[1].diagnostics().len());
+        assert!(providers[1].diagnostics()[0]
+            .message()
+            .contains("collides with provider trait"));
+    }
+
+    #[test]
+    fn provider_config_flags_malformed_tracer_args_with_catch_all() {
+        let attr: syn::Attribute = parse_quote! {
+            #[tracer(disabled_default)]
+        };
+
+        let mut diagnostics = Vec::new();
+        let config = ProviderConfig::from_attrs(&[attr], &mut diagnostics);
+
+        assert_eq!(1, diagnostics.len());
+        assert!(diagnostics[0]
+            .message()
+            .contains("must be of the form `key = value`"));
+        assert_eq!(false, config.disabled_default);
+    }
+
     #[test]
     fn provider_serde_test() {
         //Go through all of the valid test traits, parse them in to a provider, then serialize and